@@ -0,0 +1,82 @@
+//! Pre-pass for `strip_disallowed_tags = False`: turns a tag that is not on the
+//! allowlist into its literal, HTML-escaped source text instead of letting ammonia
+//! unwrap it and keep only its contents.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use regex::{Captures, Regex};
+
+fn tag_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    // A blind `[^>]*>` scan would treat a `>` embedded in a quoted attribute value
+    // (e.g. `<blink title="a>b">`) as the tag's end; match attribute values as
+    // quoted or bare tokens explicitly so such a `>` can't split the tag in two.
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?x)
+            </?([a-zA-Z][a-zA-Z0-9:-]*)
+            (?:
+                \s+[^\s=/>]+
+                (?:\s*=\s*(?:"[^"]*"|'[^']*'|[^\s>]+))?
+            )*
+            \s*/?>
+            "#,
+        )
+        .unwrap()
+    })
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Escapes the delimiters of any tag whose name is in neither `allowed` nor
+/// `clean_content`, leaving tags that ammonia will otherwise handle untouched.
+pub fn escape_disallowed_tags(
+    html: &str,
+    allowed: &HashSet<String>,
+    clean_content: &HashSet<String>,
+) -> String {
+    tag_regex()
+        .replace_all(html, |caps: &Captures| {
+            let name = caps[1].to_ascii_lowercase();
+            if allowed.contains(&name) || clean_content.contains(&name) {
+                caps[0].to_string()
+            } else {
+                escape_html(&caps[0])
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn leaves_allowed_tags_untouched() {
+        let out = escape_disallowed_tags("<p>ok</p>", &tags(&["p"]), &tags(&[]));
+        assert_eq!(out, "<p>ok</p>");
+    }
+
+    #[test]
+    fn escapes_disallowed_tags() {
+        let out = escape_disallowed_tags("<blink>no</blink>", &tags(&[]), &tags(&[]));
+        assert_eq!(out, "&lt;blink&gt;no&lt;/blink&gt;");
+    }
+
+    #[test]
+    fn quoted_angle_bracket_does_not_split_a_tag() {
+        let out = escape_disallowed_tags(
+            r#"<blink title="a>b">evil</blink>"#,
+            &tags(&[]),
+            &tags(&[]),
+        );
+        assert_eq!(out, r#"&lt;blink title="a&gt;b"&gt;evil&lt;/blink&gt;"#);
+    }
+}
@@ -2,9 +2,13 @@ use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 
 use ouroboros::self_referencing;
-use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::{PyIOError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::{PyString, PyTuple};
+use rayon::prelude::*;
+
+mod escape;
+mod linkify;
 
 struct Config {
     tags: Option<HashSet<String>>,
@@ -17,8 +21,11 @@ struct Config {
     tag_attribute_values: Option<HashMap<String, HashMap<String, HashSet<String>>>>,
     set_tag_attribute_values: Option<HashMap<String, HashMap<String, String>>>,
     url_schemes: Option<HashSet<String>>,
+    url_relative: UrlRelativePolicy,
     allowed_classes: Option<HashMap<String, HashSet<String>>>,
     filter_style_properties: Option<HashSet<String>>,
+    link: bool,
+    strip_disallowed_tags: bool,
 }
 
 impl Default for Config {
@@ -34,12 +41,51 @@ impl Default for Config {
             tag_attribute_values: None,
             set_tag_attribute_values: None,
             url_schemes: None,
+            url_relative: UrlRelativePolicy::PassThrough,
             allowed_classes: None,
             filter_style_properties: None,
+            link: false,
+            strip_disallowed_tags: true,
         }
     }
 }
 
+fn effective_allowed_tags(config: &Config) -> HashSet<String> {
+    match config.tags.as_ref() {
+        Some(tags) => tags.clone(),
+        None => ammonia::Builder::default()
+            .clone_tags()
+            .into_iter()
+            .map(|s| s.to_string())
+            .collect(),
+    }
+}
+
+fn effective_clean_content_tags(config: &Config) -> HashSet<String> {
+    match config.clean_content_tags.as_ref() {
+        Some(tags) => tags.clone(),
+        None => ["script", "style"].iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+/// The resolved form of the `url_relative` option, mirroring `ammonia::UrlRelative`
+/// but keeping the base URL parsed up front so bad input is rejected eagerly.
+enum UrlRelativePolicy {
+    PassThrough,
+    Deny,
+    RewriteWithBase(ammonia::Url),
+}
+
+fn parse_url_relative(value: &str) -> PyResult<UrlRelativePolicy> {
+    match value {
+        "pass-through" => Ok(UrlRelativePolicy::PassThrough),
+        "deny" => Ok(UrlRelativePolicy::Deny),
+        base => ammonia::Url::parse(base)
+            .map(UrlRelativePolicy::RewriteWithBase)
+            .map_err(|e| PyValueError::new_err(format!("invalid url_relative base: {e}"))),
+    }
+}
+
 #[self_referencing]
 struct Inner {
     config: Config,
@@ -177,6 +223,13 @@ impl Cleaner {
             let url_schemes: HashSet<_> = url_schemes.iter().map(|s| s.as_str()).collect();
             builder.url_schemes(url_schemes);
         }
+        builder.url_relative(match &config.url_relative {
+            UrlRelativePolicy::PassThrough => ammonia::UrlRelative::PassThrough,
+            UrlRelativePolicy::Deny => ammonia::UrlRelative::Deny,
+            UrlRelativePolicy::RewriteWithBase(base) => {
+                ammonia::UrlRelative::RewriteWithBase(base.clone())
+            }
+        });
         if let Some(allowed_classes) = config.allowed_classes.as_ref() {
             builder.allowed_classes(
                 allowed_classes
@@ -200,9 +253,89 @@ impl Cleaner {
     }
 
     pub fn clean(&self, html: &str) -> String {
+        let escaped;
+        let html = if self.inner.borrow_config().strip_disallowed_tags {
+            html
+        } else {
+            let config = self.inner.borrow_config();
+            escaped = escape::escape_disallowed_tags(
+                html,
+                &effective_allowed_tags(config),
+                &effective_clean_content_tags(config),
+            );
+            &escaped
+        };
+        let linked;
+        let html = if self.inner.borrow_config().link {
+            linked = linkify::linkify(html);
+            &linked
+        } else {
+            html
+        };
         self.inner
             .with_builder(|builder| builder.clean(html).to_string())
     }
+
+    fn clean_many(&self, inputs: &[String]) -> Vec<String> {
+        if self.inner.borrow_config().attribute_filter.is_some() {
+            inputs.iter().map(|html| self.clean(html)).collect()
+        } else {
+            inputs.par_iter().map(|html| self.clean(html)).collect()
+        }
+    }
+
+    /// Sanitizes `reader` without buffering the whole input into memory first,
+    /// unless this `Cleaner`'s `link` or `strip_disallowed_tags` options require a
+    /// prepass over the full document (see `clean`) — those two fall back to
+    /// buffering since there's no way to apply them to a stream.
+    fn clean_reader(&self, mut reader: impl std::io::Read) -> std::io::Result<String> {
+        if self.inner.borrow_config().link || !self.inner.borrow_config().strip_disallowed_tags {
+            let mut html = String::new();
+            reader.read_to_string(&mut html)?;
+            return Ok(self.clean(&html));
+        }
+        self.inner
+            .with_builder(|builder| builder.clean_from_reader(reader))
+            .map(|document| document.to_string())
+    }
+}
+
+/// Adapts a Python file-like object (anything with a `.read(size)` method returning
+/// `str` or `bytes`) into a `std::io::Read`.
+///
+/// A text-mode file object's `.read(n)` reads `n` *characters*, which for non-ASCII
+/// text can encode to more than `n` UTF-8 bytes — more than the caller's buffer has
+/// room for. Rather than reject that, any excess encoded bytes are held back and
+/// served on subsequent `read` calls.
+struct PyReadAdapter<'py> {
+    py: Python<'py>,
+    obj: PyObject,
+    leftover: Vec<u8>,
+}
+
+impl std::io::Read for PyReadAdapter<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.leftover.is_empty() {
+            let chunk = self
+                .obj
+                .call_method1(self.py, "read", (buf.len(),))
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.leftover = if let Ok(bytes) = chunk.extract::<Vec<u8>>(self.py) {
+                bytes
+            } else if let Ok(text) = chunk.extract::<String>(self.py) {
+                text.into_bytes()
+            } else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "read() must return str or bytes",
+                ));
+            };
+        }
+        let n = self.leftover.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.leftover[..n]);
+        self.leftover.drain(..n);
+        Ok(n)
+    }
 }
 
 #[pymethods]
@@ -242,6 +375,11 @@ impl Cleaner {
     /// :type set_tag_attribute_values: ``dict[str, dict[str, str]]``, optional
     /// :param url_schemes: Sets the URL schemes permitted on ``href`` and ``src`` attributes.
     /// :type url_schemes: ``set[str]``, optional
+    /// :param url_relative: Configures the handling of relative URLs in ``href``/``src`` attributes,
+    ///     defaults to ``"pass-through"``, which is ammonia's default and leaves relative URLs as-is.
+    ///     Pass ``"deny"`` to strip any attribute containing a relative URL, or an absolute base URL
+    ///     string to rewrite relative URLs against that base.
+    /// :type url_relative: ``str``
     /// :param allowed_classes: Sets the CSS classes that are allowed on specific tags.
     ///     The values is structured as a map from tag names to a set of class names.
     ///     The `class` attribute itself should not be whitelisted if this parameter is used.
@@ -252,6 +390,18 @@ impl Cleaner {
     ///     invalid declarations and @rules will be removed, with only syntactically valid
     ///     declarations kept.
     /// :type filter_style_properties: ``set[str]``, optional
+    /// :param link: Wraps bare ``http(s)://``, ``www.`` and ``mailto:`` URLs in text in
+    ///     ``<a>`` elements (an email address without ``mailto:`` is not linked),
+    ///     skipping ``<a>``, ``<pre>``, ``<code>`` and ``<style>`` subtrees, before
+    ///     running the sanitizer so generated links still get ``link_rel`` and
+    ///     scheme filtering applied. Defaults to ``False``.
+    /// :type link: ``bool``
+    /// :param strip_disallowed_tags: Configures the handling of tags that are not allowed,
+    ///     defaults to ``True``, which unwraps a disallowed tag and keeps its contents, matching
+    ///     ammonia's normal behavior. Pass ``False`` to instead HTML-escape the disallowed tag's
+    ///     markup so it is kept as visible, inert text, e.g. ``<blink>hi</blink>`` becomes
+    ///     ``&lt;blink&gt;hi&lt;/blink&gt;``.
+    /// :type strip_disallowed_tags: ``bool``
     #[new]
     #[pyo3(signature = (
         tags = None,
@@ -264,9 +414,13 @@ impl Cleaner {
         tag_attribute_values = None,
         set_tag_attribute_values = None,
         url_schemes = None,
+        url_relative = "pass-through",
         allowed_classes = None,
-        filter_style_properties = None
+        filter_style_properties = None,
+        link = false,
+        strip_disallowed_tags = true
     ))]
+    #[allow(clippy::too_many_arguments)]
     fn py_new(
         py: Python,
         tags: Option<HashSet<String>>,
@@ -279,14 +433,18 @@ impl Cleaner {
         tag_attribute_values: Option<HashMap<String, HashMap<String, HashSet<String>>>>,
         set_tag_attribute_values: Option<HashMap<String, HashMap<String, String>>>,
         url_schemes: Option<HashSet<String>>,
+        url_relative: &str,
         allowed_classes: Option<HashMap<String, HashSet<String>>>,
         filter_style_properties: Option<HashSet<String>>,
+        link: bool,
+        strip_disallowed_tags: bool,
     ) -> PyResult<Self> {
         if let Some(callback) = attribute_filter.as_ref() {
             if !callback.bind(py).is_callable() {
                 return Err(PyTypeError::new_err("attribute_filter must be callable"));
             }
         }
+        let url_relative = parse_url_relative(url_relative)?;
         let config = Config {
             tags,
             clean_content_tags,
@@ -298,8 +456,11 @@ impl Cleaner {
             tag_attribute_values,
             set_tag_attribute_values,
             url_schemes,
+            url_relative,
             allowed_classes,
             filter_style_properties,
+            link,
+            strip_disallowed_tags,
         };
         Ok(Self::new(config))
     }
@@ -309,6 +470,56 @@ impl Cleaner {
     fn py_clean(&self, py: Python, html: &str) -> PyResult<String> {
         Ok(py.allow_threads(|| self.clean(html)))
     }
+
+    /// Sanitize a batch of HTML fragments, reusing this ``Cleaner``'s configuration.
+    ///
+    /// The GIL is released once for the whole batch. When no ``attribute_filter`` is
+    /// configured, inputs are also distributed across threads with rayon so large
+    /// batches can use multiple CPU cores; since an ``attribute_filter`` callback
+    /// must call back into Python per attribute, batches run on a single thread
+    /// instead whenever one is configured, to avoid deadlocking on the GIL.
+    ///
+    /// :param html: Input HTML fragments
+    /// :type html: ``list[str]``
+    /// :return: Sanitized HTML fragments, in the same order as the input
+    /// :rtype: ``list[str]``
+    #[pyo3(name = "clean_many")]
+    fn py_clean_many(&self, py: Python, html: Vec<String>) -> PyResult<Vec<String>> {
+        Ok(py.allow_threads(|| self.clean_many(&html)))
+    }
+
+    /// Sanitize an HTML document read from a file-like object, applying this
+    /// ``Cleaner``'s options (including ``link`` and ``strip_disallowed_tags``)
+    /// exactly as ``clean`` does.
+    ///
+    /// :param fileobj: A readable file-like object whose ``read(size)`` method
+    ///     returns ``str`` or ``bytes`` chunks.
+    /// :param out: A writable file-like object. If given, the sanitized output is
+    ///     written to it via ``out.write()`` and ``None`` is returned instead of a ``str``.
+    /// :type out: file-like object, optional
+    /// :rtype: ``str | None``
+    #[pyo3(name = "clean_reader", signature = (fileobj, out = None))]
+    fn py_clean_reader(
+        &self,
+        py: Python,
+        fileobj: PyObject,
+        out: Option<PyObject>,
+    ) -> PyResult<Option<String>> {
+        let cleaned = self
+            .clean_reader(PyReadAdapter {
+                py,
+                obj: fileobj,
+                leftover: Vec::new(),
+            })
+            .map_err(|e| PyIOError::new_err(e.to_string()))?;
+        match out {
+            Some(out) => {
+                out.call_method1(py, "write", (cleaned,))?;
+                Ok(None)
+            }
+            None => Ok(Some(cleaned)),
+        }
+    }
 }
 
 /// Sanitize an HTML fragment according to the given options.
@@ -370,8 +581,11 @@ impl Cleaner {
     tag_attribute_values = None,
     set_tag_attribute_values = None,
     url_schemes = None,
+    url_relative = "pass-through",
     allowed_classes = None,
-    filter_style_properties = None
+    filter_style_properties = None,
+    link = false,
+    strip_disallowed_tags = true
 ))]
 #[allow(clippy::too_many_arguments)]
 fn clean(
@@ -387,8 +601,11 @@ fn clean(
     tag_attribute_values: Option<HashMap<String, HashMap<String, HashSet<String>>>>,
     set_tag_attribute_values: Option<HashMap<String, HashMap<String, String>>>,
     url_schemes: Option<HashSet<String>>,
+    url_relative: &str,
     allowed_classes: Option<HashMap<String, HashSet<String>>>,
     filter_style_properties: Option<HashSet<String>>,
+    link: bool,
+    strip_disallowed_tags: bool,
 ) -> PyResult<String> {
     let cleaner = Cleaner::py_new(
         py,
@@ -402,12 +619,162 @@ fn clean(
         tag_attribute_values,
         set_tag_attribute_values,
         url_schemes,
+        url_relative,
         allowed_classes,
         filter_style_properties,
+        link,
+        strip_disallowed_tags,
     )?;
     Ok(py.allow_threads(|| cleaner.clean(html)))
 }
 
+/// Sanitize an HTML document read from a file-like object according to the given
+/// options. See ``Cleaner()`` for detailed sanitizer options.
+///
+/// :param fileobj: A readable file-like object whose ``read(size)`` method returns
+///     ``str`` or ``bytes`` chunks.
+/// :param out: A writable file-like object. If given, the sanitized output is written
+///     to it via ``out.write()`` and ``None`` is returned instead of a ``str``.
+/// :type out: file-like object, optional
+/// :rtype: ``str | None``
+#[pyfunction(signature = (
+    fileobj,
+    out = None,
+    tags = None,
+    clean_content_tags = None,
+    attributes = None,
+    attribute_filter = None,
+    strip_comments = true,
+    link_rel = "noopener noreferrer",
+    generic_attribute_prefixes = None,
+    tag_attribute_values = None,
+    set_tag_attribute_values = None,
+    url_schemes = None,
+    url_relative = "pass-through",
+    allowed_classes = None,
+    filter_style_properties = None,
+    link = false,
+    strip_disallowed_tags = true
+))]
+#[allow(clippy::too_many_arguments)]
+fn clean_from_reader(
+    py: Python,
+    fileobj: PyObject,
+    out: Option<PyObject>,
+    tags: Option<HashSet<String>>,
+    clean_content_tags: Option<HashSet<String>>,
+    attributes: Option<HashMap<String, HashSet<String>>>,
+    attribute_filter: Option<PyObject>,
+    strip_comments: bool,
+    link_rel: Option<&str>,
+    generic_attribute_prefixes: Option<HashSet<String>>,
+    tag_attribute_values: Option<HashMap<String, HashMap<String, HashSet<String>>>>,
+    set_tag_attribute_values: Option<HashMap<String, HashMap<String, String>>>,
+    url_schemes: Option<HashSet<String>>,
+    url_relative: &str,
+    allowed_classes: Option<HashMap<String, HashSet<String>>>,
+    filter_style_properties: Option<HashSet<String>>,
+    link: bool,
+    strip_disallowed_tags: bool,
+) -> PyResult<Option<String>> {
+    let cleaner = Cleaner::py_new(
+        py,
+        tags,
+        clean_content_tags,
+        attributes,
+        attribute_filter,
+        strip_comments,
+        link_rel,
+        generic_attribute_prefixes,
+        tag_attribute_values,
+        set_tag_attribute_values,
+        url_schemes,
+        url_relative,
+        allowed_classes,
+        filter_style_properties,
+        link,
+        strip_disallowed_tags,
+    )?;
+    cleaner.py_clean_reader(py, fileobj, out)
+}
+
+/// Wrap bare ``http(s)://``, ``www.`` and ``mailto:`` URLs in ``<a>`` elements before
+/// sanitizing. An email address without ``mailto:`` is not linked.
+///
+/// Equivalent to ``nh3.clean(html, link=True, ...)``: existing ``<a>``, ``<pre>``,
+/// ``<code>`` and ``<style>`` subtrees are left untouched, and the generated links
+/// still go through the normal sanitizer so they get ``link_rel`` and scheme
+/// filtering applied. See ``Cleaner()`` for detailed sanitizer options.
+///
+/// :param html: Input HTML fragment
+/// :type html: ``str``
+/// :return: Sanitized HTML fragment with bare URLs linkified
+/// :rtype: ``str``
+///
+/// For example:
+///
+/// .. code-block:: pycon
+///
+///     >>> import nh3
+///     >>> nh3.linkify("Contact us at https://example.com/contact")
+///     'Contact us at <a href="https://example.com/contact" rel="noopener noreferrer">https://example.com/contact</a>'
+#[pyfunction(name = "linkify", signature = (
+    html,
+    tags = None,
+    clean_content_tags = None,
+    attributes = None,
+    attribute_filter = None,
+    strip_comments = true,
+    link_rel = "noopener noreferrer",
+    generic_attribute_prefixes = None,
+    tag_attribute_values = None,
+    set_tag_attribute_values = None,
+    url_schemes = None,
+    url_relative = "pass-through",
+    allowed_classes = None,
+    filter_style_properties = None,
+    strip_disallowed_tags = true
+))]
+#[allow(clippy::too_many_arguments)]
+fn py_linkify(
+    py: Python,
+    html: &str,
+    tags: Option<HashSet<String>>,
+    clean_content_tags: Option<HashSet<String>>,
+    attributes: Option<HashMap<String, HashSet<String>>>,
+    attribute_filter: Option<PyObject>,
+    strip_comments: bool,
+    link_rel: Option<&str>,
+    generic_attribute_prefixes: Option<HashSet<String>>,
+    tag_attribute_values: Option<HashMap<String, HashMap<String, HashSet<String>>>>,
+    set_tag_attribute_values: Option<HashMap<String, HashMap<String, String>>>,
+    url_schemes: Option<HashSet<String>>,
+    url_relative: &str,
+    allowed_classes: Option<HashMap<String, HashSet<String>>>,
+    filter_style_properties: Option<HashSet<String>>,
+    strip_disallowed_tags: bool,
+) -> PyResult<String> {
+    clean(
+        py,
+        html,
+        tags,
+        clean_content_tags,
+        attributes,
+        attribute_filter,
+        strip_comments,
+        link_rel,
+        generic_attribute_prefixes,
+        tag_attribute_values,
+        set_tag_attribute_values,
+        url_schemes,
+        url_relative,
+        allowed_classes,
+        filter_style_properties,
+        true,
+        strip_disallowed_tags,
+    )
+}
+
 /// Turn an arbitrary string into unformatted HTML.
 ///
 /// Roughly equivalent to Python’s html.escape() or PHP’s htmlspecialchars and
@@ -460,6 +827,8 @@ fn is_html(py: Python, html: &str) -> bool {
 fn nh3(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
     m.add_function(wrap_pyfunction!(clean, m)?)?;
+    m.add_function(wrap_pyfunction!(clean_from_reader, m)?)?;
+    m.add_function(wrap_pyfunction!(py_linkify, m)?)?;
     m.add_function(wrap_pyfunction!(clean_text, m)?)?;
     m.add_function(wrap_pyfunction!(is_html, m)?)?;
     m.add_class::<Cleaner>()?;
@@ -470,3 +839,74 @@ fn nh3(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
     m.add("ALLOWED_URL_SCHEMES", a.clone_url_schemes())?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_cleaner(py: Python, link: bool, strip_disallowed_tags: bool, url_relative: &str) -> Cleaner {
+        Cleaner::py_new(
+            py,
+            None,
+            None,
+            None,
+            None,
+            true,
+            Some("noopener noreferrer"),
+            None,
+            None,
+            None,
+            None,
+            url_relative,
+            None,
+            None,
+            link,
+            strip_disallowed_tags,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn clean_reader_streams_when_no_prepass_is_needed() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let cleaner = test_cleaner(py, false, true, "pass-through");
+            let input = b"<p>hello <script>bad</script>world</p>".to_vec();
+            let out = cleaner.clean_reader(&input[..]).unwrap();
+            assert_eq!(out, "<p>hello world</p>");
+        });
+    }
+
+    #[test]
+    fn clean_reader_applies_link_prepass() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let cleaner = test_cleaner(py, true, true, "pass-through");
+            let input = b"Check out https://example.com for more info, thanks!".to_vec();
+            let out = cleaner.clean_reader(&input[..]).unwrap();
+            assert!(out.contains("thanks"));
+            assert!(out.contains("href=\"https://example.com\""));
+        });
+    }
+
+    #[test]
+    fn url_relative_deny_strips_relative_hrefs() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let cleaner = test_cleaner(py, false, true, "deny");
+            let out = cleaner.clean("<a href=\"/a\">a</a>");
+            assert_eq!(out, "<a rel=\"noopener noreferrer\">a</a>");
+        });
+    }
+
+    #[test]
+    fn clean_many_matches_clean_for_each_input() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let cleaner = test_cleaner(py, false, true, "pass-through");
+            let inputs = vec!["<script>bad</script>ok".to_string(), "<b>bold</b>".to_string()];
+            let out = cleaner.clean_many(&inputs);
+            assert_eq!(out, vec!["ok".to_string(), "<b>bold</b>".to_string()]);
+        });
+    }
+}
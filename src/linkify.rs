@@ -0,0 +1,199 @@
+//! Wraps bare URLs in text nodes with `<a>` elements, skipping subtrees where a
+//! link would be inappropriate (existing links, preformatted/code blocks, styles).
+//!
+//! The rewritten markup is handed back as a string; callers are expected to run it
+//! back through [`ammonia::Builder::clean`] so the generated anchors still get
+//! `link_rel`/scheme filtering applied like any other markup.
+
+use html5ever::serialize::{serialize, SerializeOpts};
+use html5ever::tendril::TendrilSink;
+use html5ever::{local_name, namespace_url, ns, parse_fragment, ParseOpts, QualName};
+use markup5ever_rcdom::{Handle, NodeData, RcDom, SerializableHandle};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::Regex;
+use std::mem;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+const SKIP_TAGS: [&str; 4] = ["a", "pre", "code", "style"];
+
+const HREF_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b'"')
+    .add(b'\'')
+    .add(b'<')
+    .add(b'>')
+    .add(b' ');
+
+fn url_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?x)
+            mailto:[[:word:].+-]+@[[:word:].-]+\.[[:alpha:]]{2,}
+            | www\.[[:word:]-]+(?:\.[[:word:]-]+)*\.[[:alpha:]]{2,}(?:/[^\s<>"']*)?
+            | https?://[[:word:]-]+(?:\.[[:word:]-]+)*(?::\d+)?(?:/[^\s<>"']*)?
+            "#,
+        )
+        .unwrap()
+    })
+}
+
+fn escape_text(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn percent_encode_href(href: &str) -> String {
+    // `&` is meaningful in a URL (query parameter separator) and must not be
+    // percent-encoded; escape it as an HTML entity instead, purely for safe
+    // embedding in the `href="..."` attribute we're about to emit.
+    utf8_percent_encode(href, HREF_ENCODE_SET)
+        .to_string()
+        .replace('&', "&amp;")
+}
+
+fn href_for(matched: &str) -> String {
+    if matched.starts_with("www.") {
+        format!("https://{matched}")
+    } else {
+        matched.to_string()
+    }
+}
+
+/// Finds bare URLs in `text` and returns a fragment of HTML with each one wrapped
+/// in an `<a>` element, or `None` if `text` contains no linkifiable URLs.
+fn linkify_text(text: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut last = 0;
+    let mut found = false;
+    for m in url_regex().find_iter(text) {
+        let trimmed = m.as_str().trim_end_matches(['.', ',', ')', '!']);
+        let end = m.start() + trimmed.len();
+        if end <= m.start() {
+            continue;
+        }
+        found = true;
+        out.push_str(&escape_text(&text[last..m.start()]));
+        let matched = &text[m.start()..end];
+        out.push_str("<a href=\"");
+        out.push_str(&percent_encode_href(&href_for(matched)));
+        out.push_str("\">");
+        out.push_str(&escape_text(matched));
+        out.push_str("</a>");
+        last = end;
+    }
+    if !found {
+        return None;
+    }
+    out.push_str(&escape_text(&text[last..]));
+    Some(out)
+}
+
+fn parse_fragment_dom(html: &str) -> RcDom {
+    let context_name = QualName::new(None, ns!(html), local_name!("div"));
+    parse_fragment(RcDom::default(), ParseOpts::default(), context_name, vec![]).one(html)
+}
+
+fn walk_children(handle: &Handle, in_skip: bool) {
+    let mut i = 0;
+    loop {
+        let len = handle.children.borrow().len();
+        if i >= len {
+            break;
+        }
+        let child = handle.children.borrow()[i].clone();
+        match &child.data {
+            NodeData::Text { contents } => {
+                let mut advanced = false;
+                if !in_skip {
+                    let text = contents.borrow().to_string();
+                    if let Some(replacement_html) = linkify_text(&text) {
+                        let replacement = parse_fragment_dom(&replacement_html);
+                        // `replacement.document`'s only child is a synthetic `<html>`
+                        // wrapper; the parsed text/`<a>`/text nodes we actually want are
+                        // *its* children. Take them out (and re-home their parent
+                        // pointers) rather than splicing the wrapper itself into the real
+                        // tree: `replacement` still owns that wrapper, and `Node`'s `Drop`
+                        // impl empties every descendant's children when it runs, which
+                        // would wipe these nodes out from under us if they were still
+                        // reachable as the wrapper's children when `replacement` drops.
+                        let wrapper = replacement.document.children.borrow()[0].clone();
+                        let new_nodes: Vec<Handle> =
+                            mem::take(&mut *wrapper.children.borrow_mut());
+                        for node in &new_nodes {
+                            node.parent.set(Some(Rc::downgrade(handle)));
+                        }
+                        let count = new_nodes.len();
+                        handle.children.borrow_mut().splice(i..=i, new_nodes);
+                        i += count;
+                        advanced = true;
+                    }
+                }
+                if !advanced {
+                    i += 1;
+                }
+            }
+            NodeData::Element { name, .. } => {
+                let skip_this = SKIP_TAGS.contains(&name.local.as_ref());
+                walk_children(&child, in_skip || skip_this);
+                i += 1;
+            }
+            _ => {
+                walk_children(&child, in_skip);
+                i += 1;
+            }
+        }
+    }
+}
+
+fn serialize_children(handle: &Handle) -> String {
+    let mut buf = Vec::new();
+    for child in handle.children.borrow().iter() {
+        let serializable: SerializableHandle = child.clone().into();
+        serialize(&mut buf, &serializable, SerializeOpts::default())
+            .expect("writing to an in-memory buffer cannot fail");
+    }
+    String::from_utf8(buf).expect("html5ever serializes only valid UTF-8")
+}
+
+/// Wraps bare `http(s)://`, `www.` and `mailto:` runs of text in `<a>` elements,
+/// leaving markup inside existing links, `<pre>`, `<code>` and `<style>` untouched.
+pub fn linkify(html: &str) -> String {
+    let dom = parse_fragment_dom(html);
+    walk_children(&dom.document, false);
+    serialize_children(&dom.document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_a_bare_url_without_losing_surrounding_text() {
+        let out = linkify("Check out https://example.com for more info, thanks!");
+        assert_eq!(
+            out,
+            "Check out <a href=\"https://example.com\">https://example.com</a> for more info, thanks!"
+        );
+    }
+
+    #[test]
+    fn wraps_mailto_and_www_links() {
+        assert_eq!(
+            linkify("contact mailto:foo@example.com please"),
+            "contact <a href=\"mailto:foo@example.com\">mailto:foo@example.com</a> please"
+        );
+        assert_eq!(
+            linkify("see www.example.com/path?a=1&b=2."),
+            "see <a href=\"https://www.example.com/path?a=1&amp;b=2\">www.example.com/path?a=1&amp;b=2</a>."
+        );
+    }
+
+    #[test]
+    fn leaves_existing_links_and_pre_blocks_untouched() {
+        assert_eq!(
+            linkify("already <a href=\"https://x.com\">https://x.com</a> here"),
+            "already <a href=\"https://x.com\">https://x.com</a> here"
+        );
+        assert_eq!(linkify("<pre>https://example.com</pre>"), "<pre>https://example.com</pre>");
+    }
+}